@@ -0,0 +1,128 @@
+//! A format-aware pixel buffer, with the pack/unpack helpers and cross-format `blit` used to get
+//! a `Drawer`'s frame into whatever layout a display or exporter wants.
+
+/// Pixel layouts a `Framebuffer` can store.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// 8 bits per channel packed as `0x00RRGGBB` — the layout `Drawer` rasterizes into, and what
+    /// `softbuffer` expects. The high byte is a reserved/unused `x`, not an alpha channel — this
+    /// format carries no transparency.
+    Bgrx8888,
+    /// 5/6/5-bit channels packed into the low 16 bits.
+    Rgb565,
+    /// 8-bit luma in the low byte.
+    Mono8,
+}
+
+impl Format {
+    /// Bytes used per pixel when serialized by `Framebuffer::dump`.
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            Format::Bgrx8888 => 4,
+            Format::Rgb565 => 2,
+            Format::Mono8 => 1,
+        }
+    }
+
+    /// Packs a full-precision sRGB color into this format's representation.
+    pub fn pack(self, r: u8, g: u8, b: u8) -> u32 {
+        match self {
+            Format::Bgrx8888 => (b as u32) | (g as u32) << 8 | (r as u32) << 16,
+            Format::Rgb565 => {
+                let r5 = (r as u32 * 31 + 127) / 255;
+                let g6 = (g as u32 * 63 + 127) / 255;
+                let b5 = (b as u32 * 31 + 127) / 255;
+
+                (r5 << 11) | (g6 << 5) | b5
+            }
+            // Rec. 601 luma weights (0.299, 0.587, 0.114) for sRGB -> grayscale.
+            Format::Mono8 => (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000,
+        }
+    }
+
+    /// Unpacks this format's representation back into 8-bit-per-channel RGB.
+    pub fn unpack(self, value: u32) -> (u8, u8, u8) {
+        match self {
+            Format::Bgrx8888 => (
+                ((value >> 16) & 0xff) as u8,
+                ((value >> 8) & 0xff) as u8,
+                (value & 0xff) as u8,
+            ),
+            Format::Rgb565 => {
+                let r5 = (value >> 11) & 0x1f;
+                let g6 = (value >> 5) & 0x3f;
+                let b5 = value & 0x1f;
+
+                (
+                    ((r5 * 255 + 15) / 31) as u8,
+                    ((g6 * 255 + 31) / 63) as u8,
+                    ((b5 * 255 + 15) / 31) as u8,
+                )
+            }
+            Format::Mono8 => {
+                let luma = (value & 0xff) as u8;
+
+                (luma, luma, luma)
+            }
+        }
+    }
+}
+
+/// An owned, row-major pixel buffer in a particular `Format`.
+pub struct Framebuffer {
+    pub format: Format,
+    pub width: u32,
+    pixels: Vec<u32>,
+}
+
+impl Framebuffer {
+    pub fn new(format: Format, width: u32, height: u32) -> Self {
+        Self {
+            format,
+            width,
+            pixels: vec![0; (width * height) as usize],
+        }
+    }
+
+    pub fn get(&self, x: u32, y: u32) -> u32 {
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, value: u32) {
+        self.pixels[(y * self.width + x) as usize] = value;
+    }
+
+    /// Serializes every pixel to its format's native byte width, little-endian, row-major. A
+    /// minimal building block for exporting to raw/embedded display formats (and eventually
+    /// PNG, once there's an encoder in the mix).
+    pub fn dump(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.pixels.len() * self.format.bytes_per_pixel());
+
+        for &pixel in &self.pixels {
+            bytes.extend_from_slice(&pixel.to_le_bytes()[..self.format.bytes_per_pixel()]);
+        }
+
+        bytes
+    }
+}
+
+/// Converts the `size` rect at `src_origin` in `src` into `dst` at `dst_origin`, re-encoding
+/// each pixel from `src.format` to `dst.format`.
+pub fn blit(
+    src: &Framebuffer,
+    src_origin: (u32, u32),
+    size: (u32, u32),
+    dst: &mut Framebuffer,
+    dst_origin: (u32, u32),
+) {
+    for y in 0..size.1 {
+        for x in 0..size.0 {
+            let (r, g, b) = src
+                .format
+                .unpack(src.get(src_origin.0 + x, src_origin.1 + y));
+            let packed = dst.format.pack(r, g, b);
+
+            dst.set(dst_origin.0 + x, dst_origin.1 + y, packed);
+        }
+    }
+}
@@ -1,9 +1,16 @@
-use std::{num::NonZeroU32, rc::Rc};
+mod framebuffer;
+mod output;
+mod texture;
 
-use glam::{UVec2, Vec2, Vec3, Vec3Swizzles};
-use mesh_loader::Loader;
+use std::{num::NonZeroU32, path::PathBuf, rc::Rc};
+
+use framebuffer::{Format, Framebuffer};
+use glam::{Mat4, UVec2, Vec2, Vec3, Vec3Swizzles};
+use mesh_loader::{Loader, Scene};
+use output::{ColorMode, Output, TerminalOutput, WindowOutput};
 use palette::Srgb;
-use softbuffer::{Buffer, SoftBufferError};
+use rayon::prelude::*;
+use texture::{Filter, Texture};
 use winit::{
     error::EventLoopError,
     event::{Event, WindowEvent},
@@ -11,24 +18,401 @@ use winit::{
     window::{Window, WindowBuilder},
 };
 
+/// Default resolution used for one-shot `--export` frames, which have no window or terminal to
+/// size themselves against.
+const EXPORT_SIZE: (u32, u32) = (512, 512);
+
+/// Default interpupillary offset (world units) between the two eyes when `--stereo` is set.
+const DEFAULT_IPD: f32 = 0.06;
+
+/// Default shadow-map depth bias, before slope scaling. Trade-off: too small reintroduces shadow
+/// acne, too large detaches shadows from their casters ("peter-panning").
+const DEFAULT_SHADOW_BIAS: f32 = 0.02;
+
+/// Default radius of the Poisson-disc PCF kernel, in shadow-map texels. Larger softens shadow
+/// edges at the cost of more depth comparisons per fragment.
+const DEFAULT_SHADOW_FILTER_SIZE: f32 = 1.5;
+
+/// World-space depth (Z) at which the left and right eye projections coincide (the "zero
+/// disparity" plane) when `--stereo` is set. The scene is assumed to fit within `[-1, 1]`, so the
+/// midpoint keeps roughly half the scene in front of the screen and half behind it.
+const STEREO_CONVERGENCE: f32 = 0.0;
+
 fn main() -> Result<(), EventLoopError> {
     env_logger::init();
 
+    let args: Vec<String> = std::env::args().collect();
+
     let loader = Loader::default();
     let scene = loader.load_obj("test.obj").unwrap();
 
-    let event_loop = EventLoop::new().unwrap();
-    let window = Rc::new(WindowBuilder::new().build(&event_loop).unwrap());
-    let context = softbuffer::Context::new(window.clone()).unwrap();
-    let mut surface = softbuffer::Surface::new(&context, window.clone()).unwrap();
+    let light_dir = Vec3::new(0.0, 0.0, -1.0);
+    let (shadow_bias, shadow_filter_size) = parse_shadow_args(&args);
+    let shadow_map =
+        ShadowMap::new(1024, light_dir, shadow_bias, shadow_filter_size).render(&scene);
+    let texture = Texture::load("test.png").ok();
+
+    let stereo = parse_stereo_args(&args);
+    let texture_filter = parse_texture_filter_args(&args);
+
+    if let Some((path, format)) = parse_export_args(&args) {
+        export_frame(
+            &scene,
+            light_dir,
+            &shadow_map,
+            texture.as_ref(),
+            stereo,
+            texture_filter,
+            format,
+            &path,
+        );
+        Ok(())
+    } else if args.iter().any(|arg| arg == "--terminal") {
+        run_terminal(
+            scene,
+            light_dir,
+            shadow_map,
+            texture,
+            stereo,
+            texture_filter,
+        );
+        Ok(())
+    } else {
+        run_window(
+            scene,
+            light_dir,
+            shadow_map,
+            texture,
+            stereo,
+            texture_filter,
+        )
+    }
+}
+
+/// Parses `--export <path> [--format bgrx8888|rgb565|mono8]` from the command line.
+fn parse_export_args(args: &[String]) -> Option<(PathBuf, Format)> {
+    let path = args
+        .iter()
+        .position(|arg| arg == "--export")
+        .and_then(|idx| args.get(idx + 1))?
+        .into();
+
+    let format = match args
+        .iter()
+        .position(|arg| arg == "--format")
+        .and_then(|idx| args.get(idx + 1))
+        .map(String::as_str)
+    {
+        Some("rgb565") => Format::Rgb565,
+        Some("mono8") => Format::Mono8,
+        _ => Format::Bgrx8888,
+    };
+
+    Some((path, format))
+}
+
+/// Parses `--shadow-bias <bias>` and `--shadow-filter-size <texels>` from the command line, for
+/// trading shadow softness against rasterization cost without a recompile.
+fn parse_shadow_args(args: &[String]) -> (f32, f32) {
+    let bias = args
+        .iter()
+        .position(|arg| arg == "--shadow-bias")
+        .and_then(|idx| args.get(idx + 1))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_SHADOW_BIAS);
+
+    let filter_size = args
+        .iter()
+        .position(|arg| arg == "--shadow-filter-size")
+        .and_then(|idx| args.get(idx + 1))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_SHADOW_FILTER_SIZE);
+
+    (bias, filter_size)
+}
+
+/// Parses `--texture-filter nearest|bilinear` from the command line.
+fn parse_texture_filter_args(args: &[String]) -> Filter {
+    match args
+        .iter()
+        .position(|arg| arg == "--texture-filter")
+        .and_then(|idx| args.get(idx + 1))
+        .map(String::as_str)
+    {
+        Some("nearest") => Filter::Nearest,
+        _ => Filter::Bilinear,
+    }
+}
+
+/// Parses `--stereo [--ipd <world units>]` from the command line.
+fn parse_stereo_args(args: &[String]) -> Stereo {
+    if !args.iter().any(|arg| arg == "--stereo") {
+        return Stereo::Mono;
+    }
+
+    let ipd = args
+        .iter()
+        .position(|arg| arg == "--ipd")
+        .and_then(|idx| args.get(idx + 1))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_IPD);
+
+    Stereo::SideBySide { ipd }
+}
+
+/// Renders a single frame off-screen and dumps it to `path` in raw `format` pixels, for
+/// low-bit-depth or embedded displays that read a framebuffer directly rather than through a
+/// window or terminal.
+fn export_frame(
+    scene: &Scene,
+    light_dir: Vec3,
+    shadow_map: &ShadowMap,
+    texture: Option<&Texture>,
+    stereo: Stereo,
+    texture_filter: Filter,
+    format: Format,
+    path: &std::path::Path,
+) {
+    let mut drawer = Drawer::new(EXPORT_SIZE.0, EXPORT_SIZE.1);
+    drawer.clear();
+
+    draw_frame(
+        &mut drawer,
+        scene,
+        light_dir,
+        shadow_map,
+        texture,
+        stereo,
+        texture_filter,
+    );
+
+    let frame = drawer.export(format);
+    std::fs::write(path, frame.dump()).unwrap();
+}
+
+/// Whether to render one full-frame view, or two side-by-side views for stereoscopic output.
+#[derive(Clone, Copy)]
+enum Stereo {
+    Mono,
+    /// Renders the scene twice into left/right halves of the frame, each eye's viewpoint shifted
+    /// `ipd` (interpupillary distance, in world units) apart.
+    SideBySide { ipd: f32 },
+}
+
+/// Renders a whole frame according to `stereo`: either one `render_scene` pass covering the full
+/// frame, or two passes into the left/right halves with opposite eye offsets.
+fn draw_frame(
+    drawer: &mut Drawer,
+    scene: &Scene,
+    light_dir: Vec3,
+    shadow_map: &ShadowMap,
+    texture: Option<&Texture>,
+    stereo: Stereo,
+    texture_filter: Filter,
+) {
+    match stereo {
+        Stereo::Mono => {
+            let viewport = Viewport::full(drawer.screen_size());
+            render_scene(
+                drawer,
+                scene,
+                light_dir,
+                shadow_map,
+                texture,
+                texture_filter,
+                viewport,
+                0.0,
+            );
+        }
+        Stereo::SideBySide { ipd } => {
+            let screen_size = drawer.screen_size();
+            let left_width = screen_size.x / 2;
+
+            let left = Viewport {
+                origin: UVec2::ZERO,
+                size: UVec2::new(left_width, screen_size.y),
+            };
+            let right = Viewport {
+                origin: UVec2::new(left_width, 0),
+                size: UVec2::new(screen_size.x - left_width, screen_size.y),
+            };
+
+            render_scene(
+                drawer,
+                scene,
+                light_dir,
+                shadow_map,
+                texture,
+                texture_filter,
+                left,
+                -ipd * 0.5,
+            );
+            render_scene(
+                drawer,
+                scene,
+                light_dir,
+                shadow_map,
+                texture,
+                texture_filter,
+                right,
+                ipd * 0.5,
+            );
+        }
+    }
+}
+
+/// Renders one view of the scene into `viewport`: the debug axis lines plus every lit face.
+/// `eye_offset` shears the projection horizontally by an amount that grows with a vertex's depth
+/// away from `STEREO_CONVERGENCE`, for rendering the same scene from two eyes a fixed distance
+/// apart with real depth-dependent disparity (rather than a flat world-space shift, which would
+/// move every vertex by the same screen-space amount regardless of depth). Faces with UVs get
+/// textured (modulated by the shadowed flat light intensity) when `texture` is loaded; otherwise
+/// they fall back to the shadowed flat shading.
+fn render_scene(
+    drawer: &mut Drawer,
+    scene: &Scene,
+    light_dir: Vec3,
+    shadow_map: &ShadowMap,
+    texture: Option<&Texture>,
+    texture_filter: Filter,
+    viewport: Viewport,
+    eye_offset: f32,
+) {
+    drawer.set_viewport(viewport);
+
+    let origin = viewport.origin.as_vec2();
+    drawer.line(
+        origin + Vec2::new(13.0, 20.0),
+        origin + Vec2::new(80.0, 40.0),
+        Srgb::new(255, 255, 255),
+    );
+    drawer.line(
+        origin + Vec2::new(20.0, 13.0),
+        origin + Vec2::new(40.0, 80.0),
+        Srgb::new(255, 0, 0),
+    );
+    drawer.line(
+        origin + Vec2::new(80.0, 40.0),
+        origin + Vec2::new(13.0, 20.0),
+        Srgb::new(255, 0, 0),
+    );
+
+    for mesh in &scene.meshes {
+        for face in &mesh.faces {
+            let world_coords: [Vec3; 3] = [
+                mesh.vertices[face[0] as usize].into(),
+                mesh.vertices[face[1] as usize].into(),
+                mesh.vertices[face[2] as usize].into(),
+            ];
+
+            // Shearing by depth (rather than translating the world before projecting) is what
+            // gives nearer and farther geometry different amounts of horizontal disparity, the
+            // actual depth cue a stereo viewer or anaglyph compositor relies on.
+            let sheared_xy =
+                |v: Vec3| Vec2::new(v.x - eye_offset * (v.z - STEREO_CONVERGENCE), v.y);
+            let screen_coords = [
+                viewport
+                    .to_screen(sheared_xy(world_coords[0]))
+                    .extend(world_coords[0].z),
+                viewport
+                    .to_screen(sheared_xy(world_coords[1]))
+                    .extend(world_coords[1].z),
+                viewport
+                    .to_screen(sheared_xy(world_coords[2]))
+                    .extend(world_coords[2].z),
+            ];
+
+            // The shading normal and intensity use the un-shifted geometry: the directional light
+            // doesn't move with the eye, only the projection does.
+            let n = (world_coords[2] - world_coords[0])
+                .cross(world_coords[1] - world_coords[0])
+                .normalize();
+            let intensity = n.dot(light_dir);
+
+            if intensity <= 0.0 {
+                continue;
+            }
+
+            match (texture, mesh.texcoords[0].get(face[0] as usize)) {
+                (Some(texture), Some(_)) => {
+                    let uvs: [Vec2; 3] = [
+                        mesh.texcoords[0][face[0] as usize].into(),
+                        mesh.texcoords[0][face[1] as usize].into(),
+                        mesh.texcoords[0][face[2] as usize].into(),
+                    ];
+
+                    // No perspective projection yet, so every vertex sits at `w = 1`; `inv_w`
+                    // still carries through the divide so a real projection can drop in later.
+                    drawer.triangle_textured(
+                        screen_coords,
+                        world_coords,
+                        uvs,
+                        [1.0; 3],
+                        Shading {
+                            normal: n,
+                            light_dir,
+                            shadow: shadow_map,
+                        },
+                        TextureStage {
+                            texture,
+                            filter: texture_filter,
+                        },
+                    );
+                }
+                _ => {
+                    drawer.triangle_shadowed(
+                        screen_coords,
+                        world_coords,
+                        Shading {
+                            normal: n,
+                            light_dir,
+                            shadow: shadow_map,
+                        },
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Window-bound resources that only live between a `Resumed` and the next `Suspended`. On mobile
+/// and web targets the windowing system can tear the native surface down at any time (the app is
+/// backgrounded, the browser tab loses its GL context, ...), so these are rebuilt from scratch on
+/// every `Resumed` rather than created once before the event loop starts.
+struct SurfaceState {
+    window: Rc<Window>,
+    surface: softbuffer::Surface<Rc<Window>, Rc<Window>>,
+}
 
+/// Runs the usual `softbuffer` windowed renderer.
+fn run_window(
+    scene: Scene,
+    light_dir: Vec3,
+    shadow_map: ShadowMap,
+    texture: Option<Texture>,
+    stereo: Stereo,
+    texture_filter: Filter,
+) -> Result<(), EventLoopError> {
+    let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
 
+    let mut surface_state: Option<SurfaceState> = None;
     let mut frame = 0.0;
-    let mut light_dir = Vec3::new(0.0, 0.0, -1.0);
 
     event_loop.run(move |event, elwt| {
         match event {
+            Event::Resumed => {
+                let window = Rc::new(WindowBuilder::new().build(elwt).unwrap());
+                let context = softbuffer::Context::new(window.clone()).unwrap();
+                let surface = softbuffer::Surface::new(&context, window.clone()).unwrap();
+
+                surface_state = Some(SurfaceState { window, surface });
+            }
+            Event::Suspended => {
+                // The surface (and, on some platforms, the window) is invalid until the next
+                // `Resumed`; drop it now rather than hold onto a dead handle.
+                surface_state = None;
+            }
             Event::WindowEvent {
                 event: WindowEvent::CloseRequested,
                 ..
@@ -49,7 +433,9 @@ fn main() -> Result<(), EventLoopError> {
 
                 //frame += 0.01;
 
-                window.request_redraw();
+                if let Some(state) = &surface_state {
+                    state.window.request_redraw();
+                }
             }
             Event::WindowEvent {
                 event: WindowEvent::RedrawRequested,
@@ -61,81 +447,459 @@ fn main() -> Result<(), EventLoopError> {
                 // this event rather than in AboutToWait, since rendering in here allows
                 // the program to gracefully handle redraws requested by the OS.
 
+                let Some(state) = &mut surface_state else {
+                    // Can be requested before the first `Resumed` or after a `Suspended`; there's
+                    // no surface to draw into yet.
+                    return;
+                };
+
                 let (width, height) = {
-                    let size = window.inner_size();
+                    let size = state.window.inner_size();
                     (size.width, size.height)
                 };
 
-                surface
+                state
+                    .surface
                     .resize(
                         NonZeroU32::new(width).unwrap(),
                         NonZeroU32::new(height).unwrap(),
                     )
                     .unwrap();
 
-                let mut drawer = Drawer::new(surface.buffer_mut().unwrap(), width, height);
+                let mut drawer = Drawer::new(width, height);
                 drawer.clear();
 
-                drawer.line((13.0, 20.0), (80.0, 40.0), Srgb::new(255, 255, 255));
-                drawer.line((20.0, 13.0), (40.0, 80.0), Srgb::new(255, 0, 0));
-                drawer.line((80.0, 40.0), (13.0, 20.0), Srgb::new(255, 0, 0));
-
-                for mesh in &scene.meshes {
-                    for face in &mesh.faces {
-                        let world_coords: [Vec3; 3] = [
-                            mesh.vertices[face[0] as usize].into(),
-                            mesh.vertices[face[1] as usize].into(),
-                            mesh.vertices[face[2] as usize].into(),
-                        ];
-
-                        let half_screen = drawer.screen_size.as_vec2() * Vec2::splat(0.5);
-                        let screen_coords = [
-                            ((world_coords[0].xy() + Vec2::ONE) * half_screen)
-                                .extend(world_coords[0].z),
-                            ((world_coords[1].xy() + Vec2::ONE) * half_screen)
-                                .extend(world_coords[1].z),
-                            ((world_coords[2].xy() + Vec2::ONE) * half_screen)
-                                .extend(world_coords[2].z),
-                        ];
-
-                        let n = (world_coords[2] - world_coords[0])
-                            .cross(world_coords[1] - world_coords[0])
-                            .normalize();
-                        let intensity = n.dot(light_dir);
-
-                        if intensity > 0.0 {
-                            let color: Srgb<u8> =
-                                Srgb::new(intensity, intensity, intensity).into_format();
-
-                            drawer.triangle(screen_coords, color);
-                        }
-                    }
-                }
+                draw_frame(
+                    &mut drawer,
+                    &scene,
+                    light_dir,
+                    &shadow_map,
+                    texture.as_ref(),
+                    stereo,
+                    texture_filter,
+                );
 
-                drawer.finish().unwrap();
+                let mut output =
+                    WindowOutput::new(state.surface.buffer_mut().unwrap(), width, height);
+                drawer.finish(&mut output).unwrap();
             }
             _ => (),
         }
     })
 }
 
-struct Drawer<'a> {
-    buffer: Buffer<'a, Rc<Window>, Rc<Window>>,
-    zbuffer: Vec<f32>,
+/// Renders the scene to the controlling terminal instead of a window, redrawing on an interval
+/// to pick up terminal resizes. Exits the process (e.g. via Ctrl-C) to stop.
+fn run_terminal(
+    scene: Scene,
+    light_dir: Vec3,
+    shadow_map: ShadowMap,
+    texture: Option<Texture>,
+    stereo: Stereo,
+    texture_filter: Filter,
+) {
+    let mode = ColorMode::detect();
+
+    loop {
+        let (cols, rows) = output::terminal_size();
+        let width = cols;
+        // Truecolor packs two pixel rows per text row (one '▀' half-block each); Ansi256 prints
+        // one pixel per text row, so it needs half as many pixel rows to fill the same terminal.
+        let height = match mode {
+            ColorMode::Truecolor => rows * 2,
+            ColorMode::Ansi256 => rows,
+        };
+
+        let mut drawer = Drawer::new(width, height);
+        drawer.clear();
+
+        draw_frame(
+            &mut drawer,
+            &scene,
+            light_dir,
+            &shadow_map,
+            texture.as_ref(),
+            stereo,
+            texture_filter,
+        );
+
+        let mut output = TerminalOutput::new(width, height, mode);
+        drawer.finish(&mut output).unwrap();
+
+        std::thread::sleep(std::time::Duration::from_millis(33));
+    }
+}
+
+/// How far back along `light_dir` the shadow map's virtual camera sits. The scene is assumed to
+/// fit within the unit cube, so this just needs to clear it comfortably.
+const SHADOW_MAP_DISTANCE: f32 = 5.0;
+
+/// Half-width of the shadow map's orthographic frustum, covering the scene's `[-1, 1]` extent.
+const SHADOW_MAP_EXTENT: f32 = 1.5;
+
+/// A fixed Poisson-disc sampling pattern for percentage-closer filtering, in shadow-map texels
+/// scaled by the configured filter size.
+const POISSON_DISK: [Vec2; 16] = [
+    Vec2::new(-0.942_016_2, -0.399_062_16),
+    Vec2::new(0.945_586_1, -0.768_907_25),
+    Vec2::new(-0.094_184_1, -0.928_388_7),
+    Vec2::new(0.344_959_38, 0.293_877_6),
+    Vec2::new(-0.915_885_8, 0.457_714_32),
+    Vec2::new(-0.815_442_3, -0.879_124_64),
+    Vec2::new(-0.382_775_43, 0.276_768_45),
+    Vec2::new(0.974_844, 0.756_483_8),
+    Vec2::new(0.443_233_25, -0.975_115_5),
+    Vec2::new(0.537_429_8, -0.473_734_2),
+    Vec2::new(-0.264_969_1, -0.418_930_2),
+    Vec2::new(0.791_975_1, 0.190_901_88),
+    Vec2::new(-0.241_888_4, 0.997_065_07),
+    Vec2::new(-0.814_099_55, 0.914_375_9),
+    Vec2::new(0.199_841_26, 0.786_413_67),
+    Vec2::new(0.143_831_61, -0.141_007_9),
+];
+
+/// A depth-only render of the scene from a light's point of view, used to test whether a
+/// world-space point is occluded from that light.
+///
+/// The scene has no real camera projection (world coordinates are assumed to already lie in
+/// `[-1, 1]`), so the light's view is built the same way: an orthographic frustum oriented along
+/// `light_dir`, with depth compared directly in the light's view space.
+struct ShadowMap {
+    depth: Vec<f32>,
+    size: u32,
+    light_view: Mat4,
+    /// Base depth bias, scaled by surface slope against the light to avoid shadow acne.
+    bias: f32,
+    /// Radius of the Poisson-disc PCF kernel, in shadow-map texels.
+    filter_size: f32,
+}
+
+impl ShadowMap {
+    pub fn new(size: u32, light_dir: Vec3, bias: f32, filter_size: f32) -> Self {
+        let light_dir = light_dir.normalize();
+        let eye = -light_dir * SHADOW_MAP_DISTANCE;
+        let up = if light_dir.abs().dot(Vec3::Y) > 0.99 {
+            Vec3::X
+        } else {
+            Vec3::Y
+        };
+
+        Self {
+            depth: vec![f32::NEG_INFINITY; (size * size) as usize],
+            size,
+            light_view: Mat4::look_at_rh(eye, Vec3::ZERO, up),
+            bias,
+            filter_size,
+        }
+    }
+
+    /// Rasterizes every face of `scene` into the depth buffer, keeping the nearest-to-light
+    /// depth per texel. Consumes and returns `self` so it reads as a one-shot build step.
+    pub fn render(mut self, scene: &Scene) -> Self {
+        for mesh in &scene.meshes {
+            for face in &mesh.faces {
+                let world: [Vec3; 3] = [
+                    mesh.vertices[face[0] as usize].into(),
+                    mesh.vertices[face[1] as usize].into(),
+                    mesh.vertices[face[2] as usize].into(),
+                ];
+
+                self.rasterize(world);
+            }
+        }
+
+        self
+    }
+
+    /// Projects a world-space point into shadow-map texel space, keeping the light-view-space
+    /// depth in `z`.
+    fn project(&self, world: Vec3) -> Vec3 {
+        let view = self.light_view.transform_point3(world);
+        let uv = view.xy() / SHADOW_MAP_EXTENT * 0.5 + Vec2::splat(0.5);
+
+        (uv * self.size as f32).extend(view.z)
+    }
+
+    fn rasterize(&mut self, world: [Vec3; 3]) {
+        let pts = world.map(|w| self.project(w));
+
+        let size = Vec2::splat(self.size as f32);
+        let mut bboxmin = size;
+        let mut bboxmax = Vec2::ZERO;
+
+        for p in pts {
+            bboxmin = Vec2::ZERO.max(bboxmin.min(p.truncate()));
+            bboxmax = size.min(bboxmax.max(p.truncate()));
+        }
+
+        let bboxmin = bboxmin.as_uvec2();
+        let bboxmax = bboxmax.as_uvec2();
+
+        for x in bboxmin.x..bboxmax.x {
+            for y in bboxmin.y..bboxmax.y {
+                let p = UVec2::new(x, y).as_vec2().extend(0.0);
+                let bc = Drawer::barycentric(pts, p);
+
+                if bc.x >= 0.0 && bc.y >= 0.0 && bc.z >= 0.0 {
+                    let depth = pts[0].z * bc.x + pts[1].z * bc.y + pts[2].z * bc.z;
+                    let idx = (x + y * self.size) as usize;
+
+                    if self.depth[idx] < depth {
+                        self.depth[idx] = depth;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the fraction (0..1) of the PCF kernel that sees `world_pos` as lit by a light
+    /// shining along `light_dir`, given the surface `normal` at that point. Fragments outside
+    /// the shadow map's frustum are treated as lit.
+    pub fn factor(&self, world_pos: Vec3, normal: Vec3, light_dir: Vec3) -> f32 {
+        let view = self.light_view.transform_point3(world_pos);
+        let uv = view.xy() / SHADOW_MAP_EXTENT * 0.5 + Vec2::splat(0.5);
+
+        if uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0 {
+            return 1.0;
+        }
+
+        let texel = uv * self.size as f32;
+        let bias = self.bias * (1.0 - normal.dot(light_dir)).max(0.0);
+
+        let lit = POISSON_DISK
+            .iter()
+            .filter(|offset| {
+                let sample = texel + **offset * self.filter_size;
+                let x = sample.x.round().clamp(0.0, self.size as f32 - 1.0) as u32;
+                let y = sample.y.round().clamp(0.0, self.size as f32 - 1.0) as u32;
+
+                view.z >= self.depth[(x + y * self.size) as usize] - bias
+            })
+            .count();
+
+        lit as f32 / POISSON_DISK.len() as f32
+    }
+}
+
+/// Edge length of a `Drawer` tile, in pixels. Each tile owns a disjoint slice of the frame's
+/// color and depth data, so triangles can be rasterized into different tiles concurrently.
+const TILE_SIZE: u32 = 64;
+
+fn pack_color<S, C>(color: C) -> u32
+where
+    C: Into<palette::rgb::Rgb<S, u8>>,
+    S: std::fmt::Debug,
+{
+    let color = color.into();
+    color.blue as u32 | (color.green as u32) << 8 | (color.red as u32) << 16
+}
+
+/// One `TILE_SIZE`×`TILE_SIZE` (smaller at the screen edges) region of the frame, owning its own
+/// color and depth storage so it can be rasterized into independently of every other tile.
+struct Tile {
+    /// Top-left corner of this tile, in frame-buffer (row-major, y-down) pixel coordinates.
+    origin: UVec2,
+    size: UVec2,
+    color: Vec<u32>,
+    depth: Vec<f32>,
+}
+
+impl Tile {
+    fn new(origin: UVec2, size: UVec2) -> Self {
+        Self {
+            origin,
+            size,
+            color: vec![0; (size.x * size.y) as usize],
+            depth: vec![f32::NEG_INFINITY; (size.x * size.y) as usize],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.color.fill(0);
+        self.depth.fill(f32::NEG_INFINITY);
+    }
+
+    fn contains(&self, p: UVec2) -> bool {
+        p.x >= self.origin.x
+            && p.y >= self.origin.y
+            && p.x < self.origin.x + self.size.x
+            && p.y < self.origin.y + self.size.y
+    }
+
+    /// Unconditionally writes `packed` at frame-buffer pixel `p`, if it falls inside this tile.
+    /// Used for depth-agnostic drawing such as debug lines.
+    fn set(&mut self, p: UVec2, packed: u32) {
+        if !self.contains(p) {
+            return;
+        }
+
+        let local = p - self.origin;
+        self.color[(local.y * self.size.x + local.x) as usize] = packed;
+    }
+
+    /// Writes `packed` at frame-buffer pixel `p` if it falls inside this tile and `depth` passes
+    /// this tile's local depth test.
+    fn write(&mut self, p: UVec2, depth: f32, packed: u32) {
+        if !self.contains(p) {
+            return;
+        }
+
+        let local = p - self.origin;
+        let idx = (local.y * self.size.x + local.x) as usize;
+
+        if self.depth[idx] < depth {
+            self.depth[idx] = depth;
+            self.color[idx] = packed;
+        }
+    }
+}
+
+/// Clips the screen-space (math, y-up) bounding box `[bboxmin, bboxmax)` down to the portion
+/// `tile` actually owns, or `None` if they don't overlap at all. Each tile scans only its own
+/// clipped range rather than the triangle's whole bbox, so a triangle spanning N tiles does
+/// roughly one bbox's worth of work in total instead of redoing the full scan N times.
+fn tile_clip_bbox(
+    tile: &Tile,
     screen_size: UVec2,
+    bboxmin: UVec2,
+    bboxmax: UVec2,
+) -> Option<(UVec2, UVec2)> {
+    // Frame-buffer row `r` is math y `screen_size.y - r`, so rows `origin.y..origin.y + size.y`
+    // are math ys `screen_size.y - origin.y - size.y + 1..=screen_size.y - origin.y`.
+    let math_y_min = (screen_size.y + 1).saturating_sub(tile.origin.y + tile.size.y);
+    let math_y_max = (screen_size.y + 1).saturating_sub(tile.origin.y);
+
+    let clipped_min = UVec2::new(bboxmin.x.max(tile.origin.x), bboxmin.y.max(math_y_min));
+    let clipped_max = UVec2::new(
+        bboxmax.x.min(tile.origin.x + tile.size.x),
+        bboxmax.y.min(math_y_max),
+    );
+
+    if clipped_min.x < clipped_max.x && clipped_min.y < clipped_max.y {
+        Some((clipped_min, clipped_max))
+    } else {
+        None
+    }
+}
+
+/// A sub-rectangle of the full frame, in screen-space (math, y-up) pixel coordinates, that
+/// drawing calls are clipped to. Lets a single `Drawer` render more than one view into disjoint
+/// regions of the same frame — e.g. a left/right eye pair for stereoscopic output — without the
+/// views' triangles or z-buffers bleeding into each other.
+#[derive(Clone, Copy)]
+struct Viewport {
+    origin: UVec2,
+    size: UVec2,
 }
 
-impl<'a> Drawer<'a> {
-    pub fn new(buffer: Buffer<'a, Rc<Window>, Rc<Window>>, width: u32, height: u32) -> Self {
+impl Viewport {
+    /// A viewport covering the whole frame.
+    fn full(screen_size: UVec2) -> Self {
         Self {
-            buffer,
-            zbuffer: vec![f32::NEG_INFINITY; (width * height) as usize],
-            screen_size: (width, height).into(),
+            origin: UVec2::ZERO,
+            size: screen_size,
         }
     }
 
-    pub fn finish(self) -> Result<(), SoftBufferError> {
-        self.buffer.present()
+    /// Maps a `[-1, 1]` NDC-space point onto this viewport's slice of the frame, the same way
+    /// `render_scene` used to map it onto the whole screen via a `half_screen` scale.
+    fn to_screen(self, ndc: Vec2) -> Vec2 {
+        self.origin.as_vec2() + (ndc + Vec2::ONE) * self.size.as_vec2() * 0.5
+    }
+}
+
+/// Per-fragment flat-shading inputs shared by `triangle_shadowed` and `triangle_textured`: a face
+/// normal and a light direction, attenuated by a `ShadowMap` lookup at each fragment's world
+/// position.
+struct Shading<'a> {
+    normal: Vec3,
+    light_dir: Vec3,
+    shadow: &'a ShadowMap,
+}
+
+impl Shading<'_> {
+    /// Shadow-attenuated flat-shading intensity at `world_pos`.
+    fn intensity_at(&self, world_pos: Vec3) -> f32 {
+        self.normal.dot(self.light_dir) * self.shadow.factor(world_pos, self.normal, self.light_dir)
+    }
+}
+
+/// Texture and filtering inputs for `triangle_textured`, bundled into one parameter alongside
+/// `Shading` so the method doesn't collect a separate argument per input.
+struct TextureStage<'a> {
+    texture: &'a Texture,
+    filter: Filter,
+}
+
+struct Drawer {
+    tiles: Vec<Tile>,
+    tiles_across: u32,
+    screen_size: UVec2,
+    viewport: Viewport,
+}
+
+impl Drawer {
+    pub fn new(width: u32, height: u32) -> Self {
+        let tiles_across = width.div_ceil(TILE_SIZE);
+        let tiles_down = height.div_ceil(TILE_SIZE);
+
+        let tiles = (0..tiles_down)
+            .flat_map(|ty| (0..tiles_across).map(move |tx| (tx, ty)))
+            .map(|(tx, ty)| {
+                let origin = UVec2::new(tx * TILE_SIZE, ty * TILE_SIZE);
+                let size = UVec2::new(
+                    TILE_SIZE.min(width - origin.x),
+                    TILE_SIZE.min(height - origin.y),
+                );
+
+                Tile::new(origin, size)
+            })
+            .collect();
+
+        let screen_size = UVec2::new(width, height);
+
+        Self {
+            tiles,
+            tiles_across,
+            screen_size,
+            viewport: Viewport::full(screen_size),
+        }
+    }
+
+    /// Restricts every subsequent `pixel`/`line`/`triangle*` call to `viewport`, until the next
+    /// call to `set_viewport`.
+    pub fn set_viewport(&mut self, viewport: Viewport) {
+        self.viewport = viewport;
+    }
+
+    /// Presents the frame through `output`, converting to its requested format first. Converting
+    /// and presenting are the only steps that differ between backends.
+    pub fn finish(self, output: &mut dyn Output) -> std::io::Result<()> {
+        let frame = self.export(output.format());
+
+        output.present(&frame)
+    }
+
+    /// Converts the whole frame to `format`, for presenting or for exporting to a file.
+    pub fn export(&self, format: Format) -> Framebuffer {
+        let mut native =
+            Framebuffer::new(Format::Bgrx8888, self.screen_size.x, self.screen_size.y);
+
+        for y in 0..self.screen_size.y {
+            for x in 0..self.screen_size.x {
+                native.set(x, y, self.color_at(UVec2::new(x, y)));
+            }
+        }
+
+        if format == Format::Bgrx8888 {
+            return native;
+        }
+
+        let mut converted = Framebuffer::new(format, self.screen_size.x, self.screen_size.y);
+        let size = (self.screen_size.x, self.screen_size.y);
+        framebuffer::blit(&native, (0, 0), size, &mut converted, (0, 0));
+
+        converted
     }
 
     pub fn screen_size(&self) -> UVec2 {
@@ -143,9 +907,38 @@ impl<'a> Drawer<'a> {
     }
 
     pub fn clear(&mut self) {
-        for i in 0..(self.screen_size.x * self.screen_size.y) {
-            self.buffer[i as usize] = 0;
+        self.tiles.par_iter_mut().for_each(Tile::clear);
+    }
+
+    fn tile(&self, p: UVec2) -> Option<&Tile> {
+        if p.x >= self.screen_size.x || p.y >= self.screen_size.y {
+            return None;
         }
+
+        let tx = p.x / TILE_SIZE;
+        let ty = p.y / TILE_SIZE;
+
+        self.tiles.get((ty * self.tiles_across + tx) as usize)
+    }
+
+    fn tile_mut(&mut self, p: UVec2) -> Option<&mut Tile> {
+        if p.x >= self.screen_size.x || p.y >= self.screen_size.y {
+            return None;
+        }
+
+        let tx = p.x / TILE_SIZE;
+        let ty = p.y / TILE_SIZE;
+
+        self.tiles.get_mut((ty * self.tiles_across + tx) as usize)
+    }
+
+    /// Reads the packed color currently stored at frame-buffer pixel `p`, or `0` if out of
+    /// bounds.
+    fn color_at(&self, p: UVec2) -> u32 {
+        self.tile(p).map_or(0, |tile| {
+            let local = p - tile.origin;
+            tile.color[(local.y * tile.size.x + local.x) as usize]
+        })
     }
 
     pub fn pixel<P, S, C>(&mut self, pos: P, color: C)
@@ -155,12 +948,20 @@ impl<'a> Drawer<'a> {
         S: std::fmt::Debug,
     {
         let pos = pos.into();
+
+        if pos.x < self.viewport.origin.x
+            || pos.x >= self.viewport.origin.x + self.viewport.size.x
+            || pos.y < self.viewport.origin.y
+            || pos.y >= self.viewport.origin.y + self.viewport.size.y
+        {
+            return;
+        }
+
         let pos = UVec2::new(pos.x, self.screen_size.y - pos.y);
-        let color = color.into();
+        let packed = pack_color(color);
 
-        if (pos.y * self.screen_size.x + pos.x) < (self.screen_size.x * self.screen_size.y) as u32 {
-            self.buffer[(pos.y * self.screen_size.x + pos.x) as usize] =
-                color.blue as u32 | (color.green as u32) << 8 | (color.red as u32) << 16;
+        if let Some(tile) = self.tile_mut(pos) {
+            tile.set(pos, packed);
         }
     }
 
@@ -236,43 +1037,111 @@ impl<'a> Drawer<'a> {
         }
     }
 
-    pub fn triangle<P, S, C>(&mut self, pts: [P; 3], color: C)
+    /// Shared scan behind every `triangle*` entry point: clips `screen_pts`' bounding box to the
+    /// viewport, dispatches tiles in parallel, clips each tile's share of the box down to the
+    /// portion it owns (`tile_clip_bbox`), and runs the barycentric test over every fragment in
+    /// range. `shade` is called for each fragment that passes the test with its barycentric
+    /// weights and returns that fragment's packed color; the interpolated depth and tile write
+    /// are handled here so every caller gets the same z-test behavior.
+    fn rasterize<P>(&mut self, screen_pts: [P; 3], shade: impl Fn(Vec3) -> u32 + Sync)
     where
-        P: Into<Vec3> + Copy,
-        C: Into<palette::rgb::Rgb<S, u8>> + Copy,
-        S: std::fmt::Debug,
+        P: Into<Vec3> + Copy + Sync,
     {
-        let mut bboxmin = self.screen_size().as_vec2();
-        let mut bboxmax = Vec2::ZERO;
-        let clamp = self.screen_size().as_vec2();
+        let viewport_min = self.viewport.origin.as_vec2();
+        let viewport_max = (self.viewport.origin + self.viewport.size).as_vec2();
+        let mut bboxmin = viewport_max;
+        let mut bboxmax = viewport_min;
 
-        for point in pts {
-            bboxmin = Vec2::ZERO.max(bboxmin.min(point.into().truncate()));
-            bboxmax = clamp.min(bboxmax.max(point.into().truncate()));
+        for point in screen_pts {
+            bboxmin = viewport_min.max(bboxmin.min(point.into().truncate()));
+            bboxmax = viewport_max.min(bboxmax.max(point.into().truncate()));
         }
 
-        // Into integer coords
         let bboxmin = bboxmin.as_uvec2();
         let bboxmax = bboxmax.as_uvec2();
+        let screen_size = self.screen_size;
 
-        for x in bboxmin.x..bboxmax.x {
-            for y in bboxmin.y..bboxmax.y {
-                let mut p = UVec2::new(x, y).as_vec2().extend(0.0);
-                let bc_screen = Self::barycentric(pts, p);
+        self.tiles.par_iter_mut().for_each(|tile| {
+            let Some((bboxmin, bboxmax)) = tile_clip_bbox(tile, screen_size, bboxmin, bboxmax)
+            else {
+                return;
+            };
 
-                if bc_screen.x >= 0.0 && bc_screen.y >= 0.0 && bc_screen.z >= 0.0 {
-                    for i in 0..3 {
-                        p.z += pts[i].into().z * bc_screen[i];
-                    }
+            for x in bboxmin.x..bboxmax.x {
+                for y in bboxmin.y..bboxmax.y {
+                    let mut p = UVec2::new(x, y).as_vec2().extend(0.0);
+                    let bc_screen = Self::barycentric(screen_pts, p);
 
-                    let width = self.screen_size().x as f32;
-                    if self.zbuffer[(p.x + p.y * width) as usize] < p.z {
-                        self.zbuffer[(p.x + p.y * width) as usize] = p.z;
+                    if bc_screen.x >= 0.0 && bc_screen.y >= 0.0 && bc_screen.z >= 0.0 {
+                        for i in 0..3 {
+                            p.z += screen_pts[i].into().z * bc_screen[i];
+                        }
 
-                        self.pixel(p.truncate().as_uvec2(), color)
+                        let packed = shade(bc_screen);
+                        let screen_px = UVec2::new(x, screen_size.y - y);
+
+                        tile.write(screen_px, p.z, packed);
                     }
                 }
             }
-        }
+        });
+    }
+
+    /// Rasterizes `screen_pts`, flat-shading each fragment via `shading`. `world_pts` are the
+    /// same vertices as `screen_pts` before screen projection, used to recover each fragment's
+    /// world position for the shadow lookup.
+    pub fn triangle_shadowed<P>(
+        &mut self,
+        screen_pts: [P; 3],
+        world_pts: [Vec3; 3],
+        shading: Shading,
+    ) where
+        P: Into<Vec3> + Copy + Sync,
+    {
+        self.rasterize(screen_pts, |bc| {
+            let world_pos = world_pts[0] * bc.x + world_pts[1] * bc.y + world_pts[2] * bc.z;
+            let lit = shading.intensity_at(world_pos);
+            let color: Srgb<u8> = Srgb::new(lit, lit, lit).into_format();
+
+            pack_color(color)
+        });
+    }
+
+    /// Like `triangle_shadowed`, but samples `stage.texture` at each fragment's UV instead of
+    /// using a flat color. `uvs` and `inv_w` (reciprocal homogeneous depth) are interpolated in
+    /// screen space and divided out per fragment, so the mapping stays correct once `screen_pts`
+    /// comes from a real perspective projection rather than `w = 1`. `world_pts` are the same
+    /// vertices as `screen_pts` before screen projection, used to recover each fragment's world
+    /// position for the shadow lookup.
+    pub fn triangle_textured<P>(
+        &mut self,
+        screen_pts: [P; 3],
+        world_pts: [Vec3; 3],
+        uvs: [Vec2; 3],
+        inv_w: [f32; 3],
+        shading: Shading,
+        stage: TextureStage,
+    ) where
+        P: Into<Vec3> + Copy + Sync,
+    {
+        let uvs_over_w = [uvs[0] * inv_w[0], uvs[1] * inv_w[1], uvs[2] * inv_w[2]];
+
+        self.rasterize(screen_pts, |bc| {
+            let one_over_w = inv_w[0] * bc.x + inv_w[1] * bc.y + inv_w[2] * bc.z;
+            let uv =
+                (uvs_over_w[0] * bc.x + uvs_over_w[1] * bc.y + uvs_over_w[2] * bc.z) / one_over_w;
+
+            let world_pos = world_pts[0] * bc.x + world_pts[1] * bc.y + world_pts[2] * bc.z;
+            let lit_intensity = shading.intensity_at(world_pos);
+
+            let sample = stage.texture.sample(uv, stage.filter);
+            let lit: Srgb<u8> = Srgb::new(
+                (sample.red as f32 * lit_intensity) as u8,
+                (sample.green as f32 * lit_intensity) as u8,
+                (sample.blue as f32 * lit_intensity) as u8,
+            );
+
+            pack_color(lit)
+        });
     }
 }
@@ -0,0 +1,165 @@
+//! Output backends a `Drawer` frame can be flushed to. `Drawer` only ever deals in packed
+//! `0x00RRGGBB` pixels; everything backend-specific (windowing, terminal escape codes) lives
+//! behind the `Output` trait so the rasterizer itself doesn't need to know where pixels end up.
+
+use std::{
+    io::{self, Write},
+    rc::Rc,
+};
+
+use glam::UVec2;
+use softbuffer::{Buffer, SoftBufferError};
+use winit::window::Window;
+
+use crate::framebuffer::{Format, Framebuffer};
+
+/// A destination a rendered frame can be presented to.
+pub trait Output {
+    /// Pixel format `present` wants its `Framebuffer` pre-converted to.
+    fn format(&self) -> Format {
+        Format::Bgrx8888
+    }
+
+    /// Flushes a full frame, already converted to `format()`.
+    fn present(&mut self, frame: &Framebuffer) -> io::Result<()>;
+}
+
+/// Presents a `Drawer` frame through a `softbuffer` window surface.
+///
+/// `buffer` is an `Option` only so `present` can move it out to call `Buffer::present`, which
+/// takes the buffer by value; a `WindowOutput` is never reused across frames, so the `None` left
+/// behind afterwards is never observed.
+pub struct WindowOutput<'a> {
+    buffer: Option<Buffer<'a, Rc<Window>, Rc<Window>>>,
+    size: UVec2,
+}
+
+impl<'a> WindowOutput<'a> {
+    pub fn new(buffer: Buffer<'a, Rc<Window>, Rc<Window>>, width: u32, height: u32) -> Self {
+        Self {
+            buffer: Some(buffer),
+            size: UVec2::new(width, height),
+        }
+    }
+}
+
+impl<'a> Output for WindowOutput<'a> {
+    fn present(&mut self, frame: &Framebuffer) -> io::Result<()> {
+        let buffer = self.buffer.as_mut().expect("WindowOutput presented twice");
+
+        for y in 0..self.size.y {
+            for x in 0..self.size.x {
+                buffer[(y * self.size.x + x) as usize] = frame.get(x, y);
+            }
+        }
+
+        self.buffer
+            .take()
+            .unwrap()
+            .present()
+            .map_err(|err: SoftBufferError| io::Error::other(err.to_string()))
+    }
+}
+
+/// How a `TerminalOutput` encodes color in its escape sequences.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// Full 24-bit color (`ESC[38;2;r;g;bm`), using a half-block per two pixel rows.
+    Truecolor,
+    /// Quantized to the xterm 256-color palette, one full-block character per pixel. Used as a
+    /// low-resolution fallback for terminals that don't advertise truecolor support.
+    Ansi256,
+}
+
+impl ColorMode {
+    /// Picks truecolor if `COLORTERM` advertises it, falling back to the 256-color palette.
+    pub fn detect() -> Self {
+        match std::env::var("COLORTERM") {
+            Ok(value) if value == "truecolor" || value == "24bit" => ColorMode::Truecolor,
+            _ => ColorMode::Ansi256,
+        }
+    }
+}
+
+/// Presents a `Drawer` frame to a text terminal using ANSI escape codes.
+///
+/// In `Truecolor` mode, two vertically-stacked pixels are drawn as one '▀' cell: the top pixel
+/// sets the foreground color, the bottom sets the background, so an `H`-row frame prints as
+/// `H / 2` text rows. In `Ansi256` mode every pixel gets its own '█' cell, colored by
+/// quantizing it into the 6×6×6 color cube of the 256-color palette (see `ansi256`), since
+/// there is no cheap way to blend two pixels into a single indexed color.
+pub struct TerminalOutput {
+    size: UVec2,
+    mode: ColorMode,
+}
+
+impl TerminalOutput {
+    pub fn new(width: u32, height: u32, mode: ColorMode) -> Self {
+        Self {
+            size: UVec2::new(width, height),
+            mode,
+        }
+    }
+}
+
+impl Output for TerminalOutput {
+    fn present(&mut self, frame: &Framebuffer) -> io::Result<()> {
+        let mut out = io::stdout().lock();
+        write!(out, "\x1b[H")?;
+
+        match self.mode {
+            ColorMode::Truecolor => {
+                for y in (0..self.size.y).step_by(2) {
+                    for x in 0..self.size.x {
+                        let (tr, tg, tb) = frame.format.unpack(frame.get(x, y));
+                        let (br, bg, bb) = if y + 1 < self.size.y {
+                            frame.format.unpack(frame.get(x, y + 1))
+                        } else {
+                            (0, 0, 0)
+                        };
+
+                        write!(
+                            out,
+                            "\x1b[38;2;{tr};{tg};{tb}m\x1b[48;2;{br};{bg};{bb}m\u{2580}"
+                        )?;
+                    }
+
+                    write!(out, "\x1b[0m\r\n")?;
+                }
+            }
+            ColorMode::Ansi256 => {
+                for y in 0..self.size.y {
+                    for x in 0..self.size.x {
+                        let (r, g, b) = frame.format.unpack(frame.get(x, y));
+                        write!(out, "\x1b[38;5;{}m\u{2588}", ansi256(r, g, b))?;
+                    }
+
+                    write!(out, "\x1b[0m\r\n")?;
+                }
+            }
+        }
+
+        out.flush()
+    }
+}
+
+/// Quantizes an 8-bit-per-channel color down to the xterm 256-color cube (indices 16..231).
+fn ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| (c as u16 * 5 / 255) as u8;
+
+    16 + 36 * level(r) + 6 * level(g) + level(b)
+}
+
+/// Queries the controlling terminal's size, in character cells, falling back to a conservative
+/// default if stdout isn't a terminal (e.g. when output is piped).
+pub fn terminal_size() -> (u32, u32) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+
+    let ok = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0;
+
+    if ok && size.ws_col > 0 && size.ws_row > 0 {
+        (size.ws_col as u32, size.ws_row as u32)
+    } else {
+        (80, 24)
+    }
+}
@@ -0,0 +1,74 @@
+//! Image sampling for `Drawer::triangle_textured`.
+
+use glam::Vec2;
+use image::Rgb;
+use palette::Srgb;
+
+/// How a `Texture` samples between texel centers.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Bilinear,
+}
+
+/// A loaded image, sampled in `[0, 1]` UV space with `(0, 0)` at the bottom-left to match the
+/// OBJ/`mesh_loader` UV convention (images are row 0 at the top, so rows are flipped on lookup).
+pub struct Texture {
+    width: u32,
+    height: u32,
+    pixels: Vec<Srgb<u8>>,
+}
+
+impl Texture {
+    pub fn load(path: &str) -> image::ImageResult<Self> {
+        let image = image::open(path)?.into_rgb8();
+        let (width, height) = image.dimensions();
+        let pixels = image
+            .pixels()
+            .map(|Rgb([r, g, b])| Srgb::new(*r, *g, *b))
+            .collect();
+
+        Ok(Self {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    fn texel(&self, x: u32, y: u32) -> Srgb<u8> {
+        let x = x.min(self.width - 1);
+        let y = (self.height - 1).saturating_sub(y.min(self.height - 1));
+
+        self.pixels[(y * self.width + x) as usize]
+    }
+
+    /// Samples the texture at `uv`, wrapping coordinates outside `[0, 1]`.
+    pub fn sample(&self, uv: Vec2, filter: Filter) -> Srgb<u8> {
+        let u = uv.x.rem_euclid(1.0) * self.width as f32;
+        let v = uv.y.rem_euclid(1.0) * self.height as f32;
+
+        match filter {
+            Filter::Nearest => self.texel(u as u32, v as u32),
+            Filter::Bilinear => {
+                let (x0, y0) = (u.floor(), v.floor());
+                let (fx, fy) = (u - x0, v - y0);
+                let (x0, y0) = (x0 as u32, y0 as u32);
+                let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+
+                let lerp_channel = |a: u8, b: u8, t: f32| (a as f32 + (b as f32 - a as f32) * t) as u8;
+                let lerp = |c0: Srgb<u8>, c1: Srgb<u8>, t: f32| {
+                    Srgb::new(
+                        lerp_channel(c0.red, c1.red, t),
+                        lerp_channel(c0.green, c1.green, t),
+                        lerp_channel(c0.blue, c1.blue, t),
+                    )
+                };
+
+                let top = lerp(self.texel(x0, y0), self.texel(x1, y0), fx);
+                let bottom = lerp(self.texel(x0, y1), self.texel(x1, y1), fx);
+
+                lerp(top, bottom, fy)
+            }
+        }
+    }
+}